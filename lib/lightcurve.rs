@@ -0,0 +1,85 @@
+
+use postgres::Client;
+
+
+/// A light curve pulled from the `surveys` database, in the exact shape the periodogram expects.
+///
+/// The `weights` are already normalized to sum to 1, so the arrays can be handed straight to
+/// `lombscargle` / `lombscargle_fast` and to `auto_freq_grid` without any further massaging.
+#[derive(Debug, Clone)]
+pub struct LightCurve {
+    /// Time points, ordered ascending. No reference time is subtracted.
+    pub time: Vec<f64>,
+    /// Signal points corresponding to `time`.
+    pub signal: Vec<f64>,
+    /// Per-point standard deviations corresponding to `time`.
+    pub sigma: Vec<f64>,
+    /// Normalized weights $w_n = 1/(W \sigma_n^2)$ with $W = \sum 1/\sigma_n^2$; they sum to 1.
+    pub weights: Vec<f64>,
+}
+
+
+/// Data-access layer for the `surveys` database.
+///
+/// Bridges a database selection (a run and a source) to the in-memory arrays the periodogram works on,
+/// so the CLI never has to wrangle raw SQL or the weight normalization by hand.
+#[derive(Debug, Default)]
+pub struct SurveyData;
+
+impl SurveyData {
+
+    /// Create a new data-access layer for the `surveys` database.
+    pub fn new() -> SurveyData {
+        SurveyData
+    }
+
+    /// Fetch the `(time, signal, sigma)` time series of a single source and return it as a [`LightCurve`].
+    ///
+    /// Issues a parametrized query against the `surveys` schema, ordered by time, and converts the
+    /// per-point errors into the normalized weights the periodogram expects
+    /// ($w_n = 1/(W \sigma_n^2)$ with $W = \sum 1/\sigma_n^2$).
+    ///
+    /// # Arguments
+    ///
+    /// * `client`   - An open connection to the `surveys` database.
+    /// * `runid`    - The identifier of the run to read from.
+    /// * `sourceid` - The identifier of the source within that run.
+    ///
+    /// # Panics
+    ///
+    /// -  Panics when the query fails, when the source has no data points, or when any point has a
+    ///    non-positive `sigma` (which would produce infinite/NaN weights).
+    pub fn fetch_lightcurve(&self, client: &mut Client, runid: i32, sourceid: i64) -> LightCurve {
+
+        let query = "
+            select
+                time, signal, sigma
+            from
+                surveys.timeseries
+            where
+                runid = $1 and sourceid = $2
+            order by
+                time";
+
+        let rows = client.query(query, &[&runid, &sourceid]).expect("Query failed.");
+
+        let mut time:   Vec<f64> = Vec::with_capacity(rows.len());
+        let mut signal: Vec<f64> = Vec::with_capacity(rows.len());
+        let mut sigma:  Vec<f64> = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            time.push(row.get(0));
+            signal.push(row.get(1));
+            sigma.push(row.get(2));
+        }
+
+        assert!(!time.is_empty(), "No data points for runid {} sourceid {}.", runid, sourceid);
+        assert!(sigma.iter().all(|&s| s > 0.0), "All sigma values must be strictly positive.");
+
+        // Convert the per-point errors into normalized weights: W = Σ 1/σ², wₙ = 1/(W σₙ²).
+        let big_w: f64 = sigma.iter().map(|s| 1.0 / (s * s)).sum();
+        let weights: Vec<f64> = sigma.iter().map(|s| 1.0 / (big_w * s * s)).collect();
+
+        LightCurve { time, signal, sigma, weights }
+    }
+}